@@ -0,0 +1,286 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    error::{ParseError, ParseErrorKind},
+    options::ParseOptions,
+    parser_combinator::parse_config,
+    span::{masked_lines, Span, MASK},
+};
+
+/// How severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The input is rejected.
+    Error,
+    /// The input is accepted, but part of it is ignored.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A message about a region of a netrc file, to be shown to a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    message: String,
+    span: Span,
+    help: Option<String>,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic without a help message.
+    pub fn new<M: Into<String>>(severity: Severity, message: M, span: Span) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            span,
+            help: None,
+        }
+    }
+
+    /// Create a diagnostic for a parse error, with a help message suggesting a fix. Use
+    /// [Severity::Error] for errors returned in strict mode and [Severity::Warning] for errors
+    /// collected while parsing leniently.
+    pub fn from_parse_error(error: &ParseError, severity: Severity) -> Self {
+        let help = match error.kind() {
+            ParseErrorKind::UnexpectedToken(_) => {
+                "expected one of `machine`, `default`, `login`, `password`, `account`, `port`, \
+                 `protocol`, `path` or `macdef`"
+                    .to_string()
+            }
+            ParseErrorKind::MissingValue(keyword) => format!("add a value after `{keyword}`"),
+            ParseErrorKind::InvalidHost { .. } => {
+                "use a domain name, an IPv4 address or an IPv6 address".to_string()
+            }
+            ParseErrorKind::UnterminatedMacro(_) => "end the macro with a blank line".to_string(),
+            ParseErrorKind::UnterminatedQuote => "close the value with a `\"`".to_string(),
+            ParseErrorKind::InvalidPort(_) => "use a number between 0 and 65535".to_string(),
+            ParseErrorKind::UnquotedPassword => {
+                "put a password containing spaces between double quotes".to_string()
+            }
+        };
+
+        Self::new(severity, error.kind().to_string(), error.span()).with_help(help)
+    }
+
+    /// Attach a help message to the diagnostic.
+    pub fn with_help<H: Into<String>>(mut self, help: H) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Get the severity of the diagnostic.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Get the message of the diagnostic.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the region of the input the diagnostic is about.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Get the help message of the diagnostic.
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+}
+
+/// Render diagnostics the way rustc does: the file name and position, the offending line and a
+/// caret under the region the diagnostic is about. Every password value in the shown lines is
+/// replaced by `****`.
+///
+/// The spans of the diagnostics must point into `source`.
+pub fn render(file_name: &str, source: &str, diagnostics: &[Diagnostic]) -> String {
+    let (config, _) = parse_config(source, &ParseOptions::default());
+
+    diagnostics
+        .iter()
+        .map(|diagnostic| render_one(file_name, source, &config.password_spans, diagnostic))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_one(file_name: &str, source: &str, secrets: &[Span], diagnostic: &Diagnostic) -> String {
+    let span = diagnostic.span();
+    let line_start = source[..span.start()]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line_end = source[span.start()..]
+        .find('\n')
+        .map_or(source.len(), |newline| span.start() + newline);
+
+    let secrets: Vec<_> = secrets
+        .iter()
+        .copied()
+        .filter(|secret| secret.start() >= line_start && secret.end() <= line_end)
+        .collect();
+    let line = masked_lines(source, line_start..line_end, &secrets);
+
+    // Multi-line spans are only marked on their first line
+    let mark_start = masked_column(source, line_start, span.start(), &secrets);
+    let mark_end = masked_column(source, line_start, span.end().min(line_end), &secrets);
+
+    let number = span.line().to_string();
+    let gutter = " ".repeat(number.len());
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{}: {}", diagnostic.severity(), diagnostic.message());
+    let _ = writeln!(
+        out,
+        "{gutter}--> {file_name}:{}:{}",
+        span.line(),
+        span.column()
+    );
+    let _ = writeln!(out, "{gutter} |");
+    let _ = writeln!(out, "{number} | {line}");
+    let _ = writeln!(
+        out,
+        "{gutter} | {}{}",
+        " ".repeat(mark_start),
+        "^".repeat(mark_end.saturating_sub(mark_start).max(1))
+    );
+
+    if let Some(help) = diagnostic.help() {
+        let _ = writeln!(out, "{gutter} |");
+        let _ = writeln!(out, "{gutter} = help: {help}");
+    }
+
+    out
+}
+
+/// The column a byte offset on a line is shown at, once the secrets on the line are masked.
+fn masked_column(source: &str, line_start: usize, offset: usize, secrets: &[Span]) -> usize {
+    let mut column = 0;
+    let mut position = line_start;
+
+    for secret in secrets.iter().take_while(|secret| secret.start() < offset) {
+        column += source[position..secret.start()].chars().count();
+
+        if offset < secret.end() {
+            return column + (offset - secret.start()).min(MASK.len());
+        }
+
+        column += MASK.len();
+        position = secret.end();
+    }
+
+    column + source[position..offset].chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{netrc::Netrc, NetrcError};
+
+    use super::*;
+
+    fn strict_errors(source: &str) -> Vec<Diagnostic> {
+        match Netrc::from_str_with_options(source, &ParseOptions::new().strict(true)) {
+            Err(NetrcError::Syntax(errors)) => errors
+                .iter()
+                .map(|error| Diagnostic::from_parse_error(error, Severity::Error))
+                .collect(),
+            other => panic!("Expected syntax errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn render_invalid_host() {
+        let source = "machine example.com login user password pass\nmachine exa%mple.com\n";
+        let diagnostics = strict_errors(source);
+
+        assert_eq!(
+            render(".netrc", source, &diagnostics),
+            "\
+error: `machine` value `exa%mple.com` is not a valid host
+ --> .netrc:2:9
+  |
+2 | machine exa%mple.com
+  |         ^^^^^^^^^^^^
+  |
+  = help: use a domain name, an IPv4 address or an IPv6 address
+"
+        );
+    }
+
+    #[test]
+    fn render_masks_passwords() {
+        let source = "machine example.com password hunter2 login user oops\n";
+        let diagnostics = strict_errors(source);
+
+        assert_eq!(
+            render("netrc", source, &diagnostics),
+            "\
+error: unexpected token `oops`
+ --> netrc:1:49
+  |
+1 | machine example.com password **** login user oops
+  |                                              ^^^^
+  |
+  = help: expected one of `machine`, `default`, `login`, `password`, `account`, `port`, `protocol`, `path` or `macdef`
+"
+        );
+    }
+
+    #[test]
+    fn render_masks_unquoted_passwords() {
+        let source = "machine example.com login user password my big secret\n";
+        let diagnostics = strict_errors(source);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            render(".netrc", source, &diagnostics[1..]),
+            "\
+error: unexpected token after `password` value
+ --> .netrc:1:48
+  |
+1 | machine example.com login user password **** **** ****
+  |                                                   ^^^^
+  |
+  = help: put a password containing spaces between double quotes
+"
+        );
+    }
+
+    #[test]
+    fn render_warning_without_help() {
+        let source = "\n\n\n\n\n\n\n\n\nmachine example.com password secret";
+        let diagnostic = Diagnostic::new(
+            Severity::Warning,
+            "password stored in plain text",
+            Span::new(38, 44, 10, 30),
+        );
+
+        assert_eq!(
+            render(".netrc", source, &[diagnostic]),
+            "\
+warning: password stored in plain text
+  --> .netrc:10:30
+   |
+10 | machine example.com password ****
+   |                              ^^^^
+"
+        );
+    }
+
+    #[test]
+    fn render_multiple() {
+        let source = "machine a.com foo\nmachine b.com login";
+        let diagnostics = strict_errors(source);
+        let rendered = render(".netrc", source, &diagnostics);
+
+        assert_eq!(rendered.matches("error: ").count(), 2);
+        assert!(rendered.contains("error: missing value after `login`\n --> .netrc:2:15"));
+    }
+}
@@ -0,0 +1,172 @@
+use std::{io, string::FromUtf8Error};
+
+use crate::{permissions::PermissionViolation, span::Span};
+
+/// Result type used throughout this crate.
+pub type Result<T> = std::result::Result<T, NetrcError>;
+
+/// Errors that can occur while loading or parsing a netrc file.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum NetrcError {
+    /// Reading the netrc input failed.
+    #[error("failed to read netrc")]
+    Io(#[from] io::Error),
+    /// The netrc input is not valid UTF-8.
+    #[error("netrc is not valid UTF-8")]
+    InvalidUtf8(#[from] FromUtf8Error),
+    /// The netrc input is malformed. Only returned when parsing in strict mode, see
+    /// [crate::options::ParseOptions::strict]. Contains every problem found, in input order,
+    /// including machine names that are not valid hosts as [ParseErrorKind::InvalidHost]. The
+    /// [std::error::Error::source] of every [ParseError] is its kind.
+    #[error("malformed netrc, {}", describe_syntax(.0))]
+    Syntax(Vec<ParseError>),
+    /// A URL used for a lookup has no host to look up.
+    #[error("URL `{url}` has no host")]
+    MissingHost {
+        /// The URL without a host.
+        url: String,
+    },
+    /// The netrc file failed a permission check.
+    #[error("insecure netrc file")]
+    Permission(#[from] PermissionViolation),
+}
+
+fn describe_syntax(errors: &[ParseError]) -> String {
+    match errors {
+        [] => "no errors".to_string(),
+        [error] => error.to_string(),
+        [error, rest @ ..] => format!("{error} (and {} more)", rest.len()),
+    }
+}
+
+/// A problem found while parsing a netrc file, with the position it was found at.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("line {}, column {}: {kind}", span.line(), span.column())]
+pub struct ParseError {
+    #[source]
+    kind: ParseErrorKind,
+    span: Span,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ParseErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// What is wrong with the input.
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// The region of the input the problem was found in.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The line of the problem, starting at 1.
+    pub fn line(&self) -> usize {
+        self.span.line()
+    }
+
+    /// The column of the problem in characters, starting at 1.
+    pub fn column(&self) -> usize {
+        self.span.column()
+    }
+}
+
+/// The kinds of problems found while parsing a netrc file.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// Text was found where a keyword was expected. The entry it appears in is ignored.
+    #[error("unexpected token `{0}`")]
+    UnexpectedToken(String),
+    /// A keyword was not followed by a value.
+    #[error("missing value after `{0}`")]
+    MissingValue(String),
+    /// A `machine` value is not a valid host. The entry is kept as a [crate::host::HostKey::Opaque]
+    /// name, which only matches lookups of the exact same name.
+    #[error("`machine` value `{host}` is not a valid host")]
+    InvalidHost {
+        /// The machine name.
+        host: String,
+        /// Why the machine name is not a valid host.
+        #[source]
+        source: url::ParseError,
+    },
+    /// A `macdef` was not terminated by a blank line, so it swallowed the rest of the input.
+    #[error("macro `{0}` is not terminated by a blank line")]
+    UnterminatedMacro(String),
+    /// A quoted value is missing its closing quote, so it runs until the end of the line.
+    #[error("quoted value is not terminated")]
+    UnterminatedQuote,
+    /// The value of a `port` keyword is not a port number.
+    #[error("`port` value `{0}` is not a valid port")]
+    InvalidPort(String),
+    /// Text was found right after a `password` value, most likely the rest of a password with
+    /// spaces that is not quoted. The text is left out so it cannot leak the password. The entry
+    /// it appears in is ignored.
+    #[error("unexpected token after `password` value")]
+    UnquotedPassword,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use crate::{Netrc, ParseOptions};
+
+    use super::*;
+
+    #[test]
+    fn invalid_utf8() {
+        let err = Netrc::from_reader(&b"machine example.com password \xff"[..]).unwrap_err();
+
+        assert!(matches!(err, NetrcError::InvalidUtf8(_)));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn syntax_message() {
+        let error = |line| {
+            ParseError::new(
+                ParseErrorKind::UnexpectedToken("foo".to_string()),
+                Span::new(0, 3, line, 13),
+            )
+        };
+
+        assert_eq!(
+            NetrcError::Syntax(vec![error(2)]).to_string(),
+            "malformed netrc, line 2, column 13: unexpected token `foo`"
+        );
+        assert_eq!(
+            NetrcError::Syntax(vec![error(2), error(3), error(4)]).to_string(),
+            "malformed netrc, line 2, column 13: unexpected token `foo` (and 2 more)"
+        );
+    }
+
+    #[test]
+    fn parse_error_source_chain() {
+        let err = Netrc::from_str_with_options(
+            "machine exa%mple.com password pass",
+            &ParseOptions::new().strict(true),
+        )
+        .unwrap_err();
+        let NetrcError::Syntax(errors) = err else {
+            panic!("Expected syntax errors, got {err:?}");
+        };
+
+        let kind = errors[0].source().unwrap();
+        assert!(kind.is::<ParseErrorKind>());
+        assert!(kind.source().unwrap().is::<url::ParseError>());
+    }
+
+    #[test]
+    fn io_error_source() {
+        let err = NetrcError::from(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+
+        assert_eq!(err.to_string(), "failed to read netrc");
+        assert_eq!(err.source().unwrap().to_string(), "denied");
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use url::Host;
+
+use crate::options::HostMatchOptions;
+
+/// The name of a `machine` entry. Names that are valid hosts are parsed, so they match however
+/// the host is written. Any other name is kept as is and only matches the exact same string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HostKey {
+    /// A name that is a valid host.
+    Host(Host),
+    /// A name that is not a valid host, such as `host_with_underscore:1234`.
+    Opaque(String),
+    /// A name like `*.mirrors.corp.example`, matching every subdomain of the contained domain.
+    /// Only parsed when enabled with [crate::options::ParseOptions::wildcards].
+    Wildcard(String),
+    /// A name like `10.20.0.0/16` or `fd00::/8`, matching every address in the network. The
+    /// address has the bits outside of the prefix cleared. Only parsed when enabled with
+    /// [crate::options::ParseOptions::cidr].
+    Network {
+        /// The first address of the network.
+        address: IpAddr,
+        /// The number of leading bits of the address that are fixed.
+        prefix: u8,
+    },
+}
+
+impl HostKey {
+    /// Parse a machine name. Besides everything [Host::parse] accepts, IPv6 addresses are
+    /// accepted without the brackets a URL needs around them. Names that are not valid hosts
+    /// become [HostKey::Opaque].
+    pub fn parse(name: &str) -> Self {
+        Self::parse_with(name, &HostMatchOptions::default())
+    }
+
+    /// Parse a machine name like [HostKey::parse], following the IDNA setting of the options.
+    pub(crate) fn parse_with(name: &str, options: &HostMatchOptions) -> Self {
+        parse_key(name, options).unwrap_or_else(|_| HostKey::Opaque(name.to_string()))
+    }
+
+    /// The form of the key that is compared when matching, following the options.
+    pub(crate) fn normalized(&self, options: &HostMatchOptions) -> HostKey {
+        let strip = |name: &str| match name.strip_suffix('.') {
+            Some(stripped) if options.ignores_trailing_dot() && !stripped.is_empty() => {
+                stripped.to_string()
+            }
+            _ => name.to_string(),
+        };
+
+        match self {
+            HostKey::Host(Host::Domain(domain)) => {
+                let stripped = strip(domain);
+                if stripped.len() == domain.len() {
+                    self.clone()
+                } else {
+                    // Without the dot the name may turn out to be an address, like `1.1.1.1.`
+                    HostKey::parse_with(&stripped, options)
+                }
+            }
+            HostKey::Wildcard(domain) => HostKey::Wildcard(strip(domain)),
+            HostKey::Host(_) | HostKey::Network { .. } => self.clone(),
+            HostKey::Opaque(name) => {
+                let name = strip(name);
+                if options.is_case_insensitive() {
+                    HostKey::Opaque(name.to_lowercase())
+                } else {
+                    HostKey::Opaque(name)
+                }
+            }
+        }
+    }
+
+    /// Get the parsed host, if the name is a valid host.
+    pub fn host(&self) -> Option<&Host> {
+        match self {
+            HostKey::Host(host) => Some(host),
+            HostKey::Opaque(_) | HostKey::Wildcard(_) | HostKey::Network { .. } => None,
+        }
+    }
+
+    /// Whether the name is not a valid host and is matched as a plain string.
+    pub fn is_opaque(&self) -> bool {
+        matches!(self, HostKey::Opaque(_))
+    }
+}
+
+impl From<Host> for HostKey {
+    fn from(host: Host) -> Self {
+        HostKey::Host(host)
+    }
+}
+
+impl fmt::Display for HostKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostKey::Host(host) => write!(f, "{host}"),
+            HostKey::Opaque(name) => write!(f, "{name}"),
+            HostKey::Wildcard(domain) => write!(f, "*.{domain}"),
+            HostKey::Network { address, prefix } => write!(f, "{address}/{prefix}"),
+        }
+    }
+}
+
+/// Parse a `machine` value as a key. Names containing non-ASCII characters are kept as written
+/// when IDNA processing is disabled.
+pub(crate) fn parse_key(
+    name: &str,
+    options: &HostMatchOptions,
+) -> Result<HostKey, url::ParseError> {
+    if !options.uses_idna() && !name.is_ascii() {
+        return Ok(HostKey::Opaque(name.to_string()));
+    }
+
+    parse_host(name).map(HostKey::Host)
+}
+
+/// Parse a `machine` value like `*.mirrors.corp.example` as a wildcard. Returns `None` when the
+/// part after `*.` is not a domain name.
+pub(crate) fn parse_wildcard(name: &str, options: &HostMatchOptions) -> Option<HostKey> {
+    match parse_key(name.strip_prefix("*.")?, options) {
+        Ok(HostKey::Host(Host::Domain(domain))) => Some(HostKey::Wildcard(domain)),
+        _ => None,
+    }
+}
+
+/// Parse a `machine` value like `10.20.0.0/16` as a network. Returns `None` when the value is not
+/// an address followed by a prefix length that fits the address.
+pub(crate) fn parse_network(name: &str) -> Option<HostKey> {
+    let (address, prefix) = name.split_once('/')?;
+    let address = address.parse().ok()?;
+    let prefix = prefix.parse().ok()?;
+
+    network(address, prefix)
+}
+
+/// The network of the given prefix length containing the address. Returns `None` when the prefix
+/// is longer than the address.
+pub(crate) fn network(address: IpAddr, prefix: u8) -> Option<HostKey> {
+    let address = match address {
+        IpAddr::V4(address) if prefix <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(address) & mask))
+        }
+        IpAddr::V6(address) if prefix <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(address) & mask))
+        }
+        _ => return None,
+    };
+
+    Some(HostKey::Network { address, prefix })
+}
+
+/// Parse a `machine` value as a host, accepting IPv6 addresses without brackets.
+fn parse_host(host: &str) -> Result<Host, url::ParseError> {
+    match host.parse::<Ipv6Addr>() {
+        Ok(address) => Ok(Host::Ipv6(address)),
+        Err(_) => Host::parse(host),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hosts() {
+        assert_eq!(
+            HostKey::parse("EXAMPLE.com"),
+            HostKey::Host(Host::parse("example.com").unwrap())
+        );
+        assert_eq!(
+            HostKey::parse("::1"),
+            HostKey::Host(Host::parse("[::1]").unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_opaque_names() {
+        let key = HostKey::parse("host_with_underscore:1234");
+
+        assert!(key.is_opaque());
+        assert_eq!(key.host(), None);
+        assert_eq!(key.to_string(), "host_with_underscore:1234");
+    }
+
+    #[test]
+    fn normalize_trailing_dot() {
+        let options = HostMatchOptions::new().ignore_trailing_dot(true);
+
+        assert_eq!(
+            HostKey::parse("example.com.").normalized(&options),
+            HostKey::parse("example.com")
+        );
+        assert_eq!(
+            HostKey::parse("2.2.2.2.").normalized(&options),
+            HostKey::parse("2.2.2.2")
+        );
+        assert_eq!(
+            HostKey::parse("build:agent.").normalized(&options),
+            HostKey::Opaque("build:agent".to_string())
+        );
+        assert_eq!(
+            HostKey::parse("example.com.").normalized(&HostMatchOptions::new()),
+            HostKey::parse("example.com.")
+        );
+    }
+
+    #[test]
+    fn parse_wildcards() {
+        let options = HostMatchOptions::new();
+
+        assert_eq!(
+            parse_wildcard("*.Mirrors.Corp.Example", &options),
+            Some(HostKey::Wildcard("mirrors.corp.example".to_string()))
+        );
+        assert_eq!(
+            parse_wildcard("*.é.com", &options).unwrap().to_string(),
+            "*.xn--9ca.com"
+        );
+        assert_eq!(parse_wildcard("*.10.0.0.1", &options), None);
+        assert_eq!(parse_wildcard("mirrors.corp.example", &options), None);
+        assert_eq!(parse_wildcard("*mirrors.corp.example", &options), None);
+    }
+
+    #[test]
+    fn parse_networks() {
+        assert_eq!(
+            parse_network("10.20.3.4/16"),
+            Some(HostKey::Network {
+                address: "10.20.0.0".parse().unwrap(),
+                prefix: 16
+            })
+        );
+        assert_eq!(
+            parse_network("fd00:1::1/8").unwrap().to_string(),
+            "fd00::/8"
+        );
+        assert_eq!(parse_network("0.0.0.0/0").unwrap().to_string(), "0.0.0.0/0");
+        assert_eq!(
+            parse_network("10.0.0.1/32").unwrap().to_string(),
+            "10.0.0.1/32"
+        );
+        assert_eq!(parse_network("10.0.0.0/33"), None);
+        assert_eq!(parse_network("example.com/8"), None);
+        assert_eq!(parse_network("10.0.0.0"), None);
+    }
+
+    #[test]
+    fn normalize_case() {
+        let options = HostMatchOptions::new().case_insensitive(true);
+
+        assert_eq!(
+            HostKey::parse("Build:Agent").normalized(&options),
+            HostKey::Opaque("build:agent".to_string())
+        );
+        assert_eq!(
+            HostKey::parse("Build:Agent").normalized(&HostMatchOptions::new()),
+            HostKey::Opaque("Build:Agent".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_without_idna() {
+        let options = HostMatchOptions::new().idna(false);
+
+        assert_eq!(
+            HostKey::parse_with("é.com", &options),
+            HostKey::Opaque("é.com".to_string())
+        );
+        assert_eq!(
+            HostKey::parse_with("xn--9ca.com", &options),
+            HostKey::parse("é.com")
+        );
+    }
+}
@@ -18,10 +18,40 @@
 //! assert_eq!(entry.login(), Some("user".to_string()).as_ref());
 //! assert_eq!(entry.password(), "pass");
 //! ```
+//!
+//! When resolving credentials for many hosts, parse the file once into a [Netrc] and look entries
+//! up from there:
+//!
+//! ```rust
+//! use netrc_util::{Host, Netrc};
+//!
+//! let netrc: Netrc = "machine sample.test login user password pass".parse().unwrap();
+//! let entry = netrc.entry_for_host(&Host::parse("sample.test").unwrap()).unwrap();
+//!
+//! assert_eq!(entry.password(), "pass");
+//! ```
+#[cfg(feature = "diagnostics")]
+pub mod diagnostic;
+pub mod error;
+pub mod host;
+pub mod lookup;
+pub mod macdef;
+pub mod netrc;
 pub mod netrc_parser;
+pub mod options;
 mod parser_combinator;
+pub mod permissions;
 pub mod raw_netrc_parser;
+pub mod span;
 
+pub use crate::error::{NetrcError, ParseError, ParseErrorKind};
+pub use crate::host::HostKey;
+pub use crate::lookup::{Lookup, Match, MatchSource};
+pub use crate::macdef::Macro;
+pub use crate::netrc::{EntryRef, Netrc};
 pub use crate::netrc_parser::{NetrcParser, ValidatedEntry};
+pub use crate::options::{HostMatchOptions, ParseOptions, ValidationPolicy};
+pub use crate::permissions::{PermissionPolicy, PermissionViolation};
 pub use crate::raw_netrc_parser::{RawEntry, RawNetrcParser};
-pub use url::Host;
+pub use crate::span::Span;
+pub use url::{Host, Url};
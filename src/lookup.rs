@@ -0,0 +1,171 @@
+use crate::{host::HostKey, netrc::Netrc, netrc_parser::ValidatedEntry};
+
+/// Where the entry found by a [Lookup] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchSource {
+    /// A `machine` entry for the host itself.
+    Host,
+    /// A wildcard or network `machine` entry matching the host.
+    Pattern,
+    /// The `default` entry, used because no `machine` entry applies to the host.
+    Default,
+}
+
+/// A validated entry found by a [Lookup], together with where it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'a> {
+    entry: &'a ValidatedEntry,
+    source: MatchSource,
+}
+
+impl<'a> Match<'a> {
+    pub(crate) fn new(entry: &'a ValidatedEntry, source: MatchSource) -> Self {
+        Self { entry, source }
+    }
+
+    /// Get the entry that was found.
+    pub fn entry(&self) -> &'a ValidatedEntry {
+        self.entry
+    }
+
+    /// Get where the entry came from.
+    pub fn source(&self) -> MatchSource {
+        self.source
+    }
+
+    /// Whether the entry is the `default` entry rather than an entry for the host.
+    pub fn is_default(&self) -> bool {
+        self.source == MatchSource::Default
+    }
+}
+
+/// A lookup of the entry for a host, created with [Netrc::lookup], [Netrc::lookup_name] or
+/// [Netrc::lookup_url]. Narrow it down with the builder methods and run it with [Lookup::find].
+#[derive(Debug, Clone)]
+pub struct Lookup<'a> {
+    netrc: &'a Netrc,
+    pub(crate) host: HostKey,
+    pub(crate) port: Option<u16>,
+    pub(crate) path: Option<String>,
+    pub(crate) login: Option<String>,
+    pub(crate) default_fallback: bool,
+}
+
+impl<'a> Lookup<'a> {
+    pub(crate) fn new(netrc: &'a Netrc, host: HostKey) -> Self {
+        Self {
+            netrc,
+            host,
+            port: None,
+            path: None,
+            login: None,
+            default_fallback: true,
+        }
+    }
+
+    /// Only use entries for the given port or without a port, preferring the former. See
+    /// [Netrc::entry_for_host_and_port].
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Only use entries for a path the given URL path starts with or without a path, preferring
+    /// the longest path. See [Netrc::entry_for_url].
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// Only use the first entry with the given login. See [Netrc::entry_for_host_and_login].
+    pub fn login(mut self, login: &str) -> Self {
+        self.login = Some(login.to_string());
+        self
+    }
+
+    /// Enable or disable falling back to the `default` entry when no `machine` entry applies to
+    /// the host. Enabled by default. Disable it to never send the catch-all credentials to an
+    /// arbitrary host.
+    pub fn default_fallback(mut self, default_fallback: bool) -> Self {
+        self.default_fallback = default_fallback;
+        self
+    }
+
+    /// Run the lookup, following the same rules as [Netrc::entry_for_host].
+    ///
+    /// # Returns
+    ///
+    /// - `None` if no entry applies, or the first one that does is invalid
+    /// - `Some` with the entry and where it came from otherwise
+    pub fn find(&self) -> Option<Match<'a>> {
+        self.netrc.find(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::{Host, Url};
+
+    use crate::options::ParseOptions;
+
+    use super::*;
+
+    const INPUT: &str = "
+        machine example.com login user password pass
+        machine *.mirrors.example.com login mirror password shared
+        machine 10.0.0.0/8 login lab password range
+        default login anonymous password guest
+    ";
+
+    fn netrc() -> Netrc {
+        Netrc::from_str_with_options(INPUT, &ParseOptions::new().wildcards(true).cidr(true))
+            .unwrap()
+    }
+
+    #[test]
+    fn match_sources() {
+        let netrc = netrc();
+        let source = |name| netrc.lookup_name(name).find().map(|found| found.source());
+
+        assert_eq!(source("example.com"), Some(MatchSource::Host));
+        assert_eq!(source("a.mirrors.example.com"), Some(MatchSource::Pattern));
+        assert_eq!(source("10.1.2.3"), Some(MatchSource::Pattern));
+        assert_eq!(source("example.org"), Some(MatchSource::Default));
+
+        let found = netrc.lookup_name("example.org").find().unwrap();
+        assert!(found.is_default());
+        assert_eq!(found.entry().password(), "guest");
+    }
+
+    #[test]
+    fn without_default_fallback() {
+        let netrc = netrc();
+        let host = Host::parse("example.org").unwrap();
+
+        assert!(netrc.lookup(&host).default_fallback(false).find().is_none());
+        assert!(netrc
+            .lookup_name("example.com")
+            .default_fallback(false)
+            .find()
+            .is_some());
+        assert!(netrc
+            .lookup(&host)
+            .login("anonymous")
+            .default_fallback(false)
+            .find()
+            .is_none());
+    }
+
+    #[test]
+    fn lookup_url() {
+        let netrc = netrc();
+        let lookup = netrc
+            .lookup_url(&Url::parse("https://user@example.com:8443/repo").unwrap())
+            .unwrap();
+
+        assert_eq!(lookup.port, Some(8443));
+        assert_eq!(lookup.path.as_deref(), Some("/repo"));
+        assert_eq!(lookup.login.as_deref(), Some("user"));
+        assert_eq!(lookup.find().unwrap().source(), MatchSource::Host);
+    }
+}
@@ -0,0 +1,198 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::span::Span;
+
+/// The name of the macro ftp clients run automatically after logging in.
+pub const INIT_MACRO: &str = "init";
+
+/// A macro defined with `macdef` inside a netrc entry. Macros are used by ftp clients, which run
+/// them on request after connecting to the machine they are defined for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Macro {
+    name: String,
+    lines: Vec<String>,
+    span: Span,
+}
+
+impl Macro {
+    pub(crate) fn new(name: String, lines: Vec<String>, span: Span) -> Self {
+        Self { name, lines, span }
+    }
+
+    /// Get the name of the macro.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the body of the macro: the lines following its name, up to the first blank line.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Get the region of the input the macro was parsed from, starting at its `macdef` keyword.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Whether this is the `init` macro, which ftp clients run automatically after logging in to
+    /// the machine it is defined for.
+    pub fn is_init(&self) -> bool {
+        self.name == INIT_MACRO
+    }
+
+    /// Expand the macro into the command lines to run, following the rules of the inetutils ftp
+    /// client:
+    ///
+    /// - Leading whitespace of every line is skipped, so macros can be indented
+    /// - `$1` to `$9` (or any other number) are replaced by the argument at that position, or
+    ///   nothing if there are not enough arguments. `$0` is replaced by the macro name
+    /// - If the macro contains `$i`, its body is repeated for every argument, with `$i` replaced
+    ///   by the argument of that pass. Without arguments the body is expanded once, with `$i`
+    ///   replaced by nothing
+    /// - A backslash makes the next character literal, so `\$1` expands to `$1`
+    /// - Any other `$` is kept as is
+    pub fn expand<S: AsRef<str>>(&self, args: &[S]) -> Vec<String> {
+        let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+
+        let mut commands = vec![];
+        let mut pass = 0;
+
+        loop {
+            let mut looping = false;
+
+            for line in &self.lines {
+                commands.push(self.expand_line(line, &args, pass, &mut looping));
+            }
+
+            pass += 1;
+
+            if !looping || pass >= args.len() {
+                break;
+            }
+        }
+
+        commands
+    }
+
+    fn expand_line(&self, line: &str, args: &[&str], pass: usize, looping: &mut bool) -> String {
+        let line = line.trim_start();
+        let mut expanded = String::with_capacity(line.len());
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => expanded.extend(chars.next()),
+                '$' => match chars.peek() {
+                    Some(next) if next.is_ascii_digit() => {
+                        let position = parse_number(&mut chars);
+
+                        match position {
+                            0 => expanded.push_str(&self.name),
+                            n => expanded.push_str(args.get(n - 1).copied().unwrap_or_default()),
+                        }
+                    }
+                    Some('i') => {
+                        chars.next();
+                        *looping = true;
+                        expanded.push_str(args.get(pass).copied().unwrap_or_default());
+                    }
+                    _ => expanded.push('$'),
+                },
+                c => expanded.push(c),
+            }
+        }
+
+        expanded
+    }
+}
+
+/// Consume a run of digits. Numbers too large to be an argument position saturate, and expand to
+/// nothing.
+fn parse_number(chars: &mut Peekable<Chars<'_>>) -> usize {
+    let mut number = 0usize;
+
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        number = number.saturating_mul(10).saturating_add(digit as usize);
+        chars.next();
+    }
+
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r#macro(name: &str, lines: &[&str]) -> Macro {
+        Macro::new(
+            name.to_string(),
+            lines.iter().map(|line| line.to_string()).collect(),
+            Span::default(),
+        )
+    }
+
+    #[test]
+    fn expand_positional_arguments() {
+        let upload = r#macro("upload", &["cd $1", "put $2 $3", "echo $0"]);
+
+        assert_eq!(
+            upload.expand(&["/pub", "file.txt"]),
+            ["cd /pub", "put file.txt ", "echo upload"]
+        );
+    }
+
+    #[test]
+    fn expand_multi_digit_positions() {
+        let args: Vec<String> = (1..=10).map(|n| format!("a{n}")).collect();
+        let many = r#macro("many", &["$10 $1 $99999999999999999999999"]);
+
+        assert_eq!(many.expand(&args), ["a10 a1 "]);
+    }
+
+    #[test]
+    fn expand_loop_over_arguments() {
+        let mget = r#macro("fetch", &["get $i", "echo done $1"]);
+
+        assert_eq!(
+            mget.expand(&["a", "b", "c"]),
+            [
+                "get a",
+                "echo done a",
+                "get b",
+                "echo done a",
+                "get c",
+                "echo done a"
+            ]
+        );
+    }
+
+    #[test]
+    fn expand_loop_without_arguments() {
+        let mget = r#macro("fetch", &["get $i"]);
+
+        assert_eq!(mget.expand::<&str>(&[]), ["get "]);
+    }
+
+    #[test]
+    fn expand_escapes_and_literal_dollars() {
+        let escaped = r#macro("escaped", &["echo \\$1 $x $ \\\\ $1\\"]);
+
+        assert_eq!(escaped.expand(&["arg"]), ["echo $1 $x $ \\ arg"]);
+    }
+
+    #[test]
+    fn expand_indented_lines() {
+        let indented = r#macro("init", &["  cd /pub", "\tbinary", "    put $1  "]);
+
+        assert_eq!(
+            indented.expand(&["file.txt"]),
+            ["cd /pub", "binary", "put file.txt  "]
+        );
+    }
+
+    #[test]
+    fn init_macro() {
+        assert!(r#macro("init", &["binary"]).is_init());
+        assert!(!r#macro("initialize", &["binary"]).is_init());
+    }
+}
@@ -1,19 +1,26 @@
 use std::io::Read;
 
-use anyhow::Result;
-use url::Host;
+use url::{Host, Url};
 
-use crate::parser_combinator::{parse_config, NetrcConfig};
+use crate::{
+    error::Result,
+    netrc::Netrc,
+    options::{ParseOptions, ValidationPolicy},
+    raw_netrc_parser::RawEntry,
+};
 
-/// A netrc entry validated to have at least a password.
+/// A netrc entry validated against a [ValidationPolicy].
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ValidatedEntry {
     pub(crate) login: Option<String>,
     pub(crate) password: String,
+    /// Whether the password was set, rather than left empty by a policy not requiring one.
+    pub(crate) has_password: bool,
+    pub(crate) account: Option<String>,
 }
 
 impl ValidatedEntry {
-    fn new<T, Y>(login: T, password: Y) -> Self
+    pub(crate) fn new<T, Y>(login: T, password: Y) -> Self
     where
         T: Into<Option<String>>,
         Y: Into<String>,
@@ -21,18 +28,67 @@ impl ValidatedEntry {
         Self {
             login: login.into(),
             password: password.into(),
+            has_password: true,
+            account: None,
         }
     }
 
+    /// Validate a raw entry following the policy. The default entry is only used when the policy
+    /// merges its fields into the entry.
+    pub(crate) fn from_raw(
+        entry: &RawEntry,
+        default: Option<&RawEntry>,
+        policy: &ValidationPolicy,
+    ) -> Option<Self> {
+        let default = default.filter(|_| policy.merges_default());
+        let field = |value: fn(&RawEntry) -> Option<&String>| {
+            value(entry).or_else(|| default.and_then(value)).cloned()
+        };
+
+        let account = field(RawEntry::account);
+        let mut login = field(RawEntry::login);
+        if policy.uses_account_as_login() {
+            login = login.or_else(|| account.clone());
+        }
+
+        if policy.requires_login() && login.is_none() {
+            return None;
+        }
+
+        let password = field(RawEntry::password);
+        if policy.requires_password() && password.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            login,
+            has_password: password.is_some(),
+            password: password.unwrap_or_default(),
+            account,
+        })
+    }
+
     /// Get the login value for the entry
     pub fn login(&self) -> Option<&String> {
         self.login.as_ref()
     }
 
-    /// Get the password value for the entry
+    /// Get the account value for the entry
+    pub fn account(&self) -> Option<&String> {
+        self.account.as_ref()
+    }
+
+    /// Get the password value for the entry. Empty when the entry has no password, see
+    /// [ValidatedEntry::has_password].
     pub fn password(&self) -> &str {
         &self.password
     }
+
+    /// Whether the entry has a password. Only entries validated with a policy not requiring a
+    /// password can be without one, which tells them apart from an explicit `password ""`.
+    pub fn has_password(&self) -> bool {
+        self.has_password
+    }
 }
 
 /// Netrc parser mimicking the curl netrc parsers rules. This is a high level parser and is
@@ -41,22 +97,31 @@ impl ValidatedEntry {
 #[derive(Debug)]
 pub struct NetrcParser<R: Read> {
     buffer: R,
-    config: Option<NetrcConfig>,
+    options: ParseOptions,
+    netrc: Option<Netrc>,
 }
 
 impl<R: Read> NetrcParser<R> {
     /// Create a new parser from a buffer
     pub fn new(buffer: R) -> Self {
+        Self::with_policy(buffer, ValidationPolicy::default())
+    }
+
+    /// Create a new parser from a buffer, validating entries following the given policy instead
+    /// of the default curl rules.
+    pub fn with_policy(buffer: R, policy: ValidationPolicy) -> Self {
         Self {
             buffer,
-            config: None,
+            options: ParseOptions::new().validation(policy),
+            netrc: None,
         }
     }
 
     /// Parse the config file from the constructor and attempt to find the entry related to the
     /// given host.
     ///
-    /// This method follows the following rules:
+    /// By default this method follows the following rules, which can be changed with
+    /// [NetrcParser::with_policy]:
     ///
     /// - A entry must have a password and may have a login
     /// - Incomplete entries cannot fallback to the default entry
@@ -71,30 +136,99 @@ impl<R: Read> NetrcParser<R> {
     /// - `Ok(None)` if the host was not found and no default was setup
     /// - `Ok(Some)` if either a default was setup or the host was found
     pub fn entry_for_host(&mut self, host: &Host) -> Result<Option<ValidatedEntry>> {
-        let mut buf_content = String::new();
-        self.buffer.read_to_string(&mut buf_content)?;
+        Ok(self.netrc()?.entry_for_host(host).cloned())
+    }
 
-        let config = match &self.config {
-            Some(config) => config.clone(),
-            None => {
-                let config = parse_config(&buf_content);
-                self.config = Some(config.clone());
+    /// Parse the config file from the constructor and attempt to find the entry of the given host
+    /// for the given login. Follows the rules of [NetrcParser::entry_for_host], except that the
+    /// first entry of the host with the same login is used, falling back to the default entry
+    /// when it has the same login. Without a login this is the same as
+    /// [NetrcParser::entry_for_host].
+    ///
+    /// # Returns
+    ///
+    /// - An error if reading the input buffer failed
+    /// - `Ok(None)` if no valid entry with the login was found
+    /// - `Ok(Some)` if a valid entry with the login was found
+    pub fn entry_for_host_and_login(
+        &mut self,
+        host: &Host,
+        login: Option<&str>,
+    ) -> Result<Option<ValidatedEntry>> {
+        Ok(self.netrc()?.entry_for_host_and_login(host, login).cloned())
+    }
 
-                config
-            }
-        };
+    /// Parse the config file from the constructor and attempt to find the entry of the given host
+    /// for the given port. Follows the rules of [NetrcParser::entry_for_host], except that
+    /// entries with the same `port` are preferred over entries without a port and entries for
+    /// other ports are never used.
+    ///
+    /// # Returns
+    ///
+    /// - An error if reading the input buffer failed
+    /// - `Ok(None)` if no entry applies to the port and no default was setup
+    /// - `Ok(Some)` if either a default was setup or an entry for the port was found
+    pub fn entry_for_host_and_port(
+        &mut self,
+        host: &Host,
+        port: u16,
+    ) -> Result<Option<ValidatedEntry>> {
+        Ok(self.netrc()?.entry_for_host_and_port(host, port).cloned())
+    }
+
+    /// Parse the config file from the constructor and attempt to find the entry for the host of
+    /// the given URL. See [crate::netrc::Netrc::entry_for_url] for how the user and password in
+    /// the URL are used.
+    ///
+    /// # Returns
+    ///
+    /// - An error if reading the input buffer failed or the URL has no host
+    /// - `Ok(None)` if no valid entry was found
+    /// - `Ok(Some)` if an entry was found, or the URL contains a password
+    pub fn entry_for_url(&mut self, url: &Url) -> Result<Option<ValidatedEntry>> {
+        self.netrc()?.entry_for_url(url)
+    }
+
+    /// Parse the config file from the constructor and attempt to find the entry related to the
+    /// given machine name, following the same rules as [NetrcParser::entry_for_host]. The name is
+    /// parsed with [crate::host::HostKey::parse], so names that are not valid hosts match entries
+    /// with the exact same name.
+    ///
+    /// # Returns
+    ///
+    /// - An error if reading the input buffer failed
+    /// - `Ok(None)` if the name was not found and no default was setup
+    /// - `Ok(Some)` if either a default was setup or the name was found
+    pub fn entry_for_name(&mut self, name: &str) -> Result<Option<ValidatedEntry>> {
+        Ok(self.netrc()?.entry_for_name(name).cloned())
+    }
 
-        let entry = config.entries.get(host).or(config.default.as_ref());
-
-        match entry {
-            Some(entry) => match (
-                entry.login.as_ref().or(entry.account.as_ref()),
-                entry.password.as_ref(),
-            ) {
-                (login, Some(password)) => Ok(Some(ValidatedEntry::new(login.cloned(), password))),
-                _ => Ok(None),
-            },
-            None => Ok(None),
+    /// Parse the config file from the constructor and find every entry of the given host, in the
+    /// order they appear in the input. Invalid entries are skipped. The default entry is not
+    /// included.
+    ///
+    /// # Returns
+    ///
+    /// - An error if reading the input buffer failed
+    /// - `Ok` with the entries of the host, empty if the host was not found
+    pub fn entries_for_host(&mut self, host: &Host) -> Result<Vec<ValidatedEntry>> {
+        Ok(self.netrc()?.entries_for_host(host).cloned().collect())
+    }
+
+    /// Parse the config file from the constructor, if not done before, and get the parsed
+    /// document. Use it for lookups the parser does not offer, like [Netrc::lookup].
+    ///
+    /// # Returns
+    ///
+    /// - An error if reading the input buffer failed
+    /// - `Ok` with the parsed document otherwise
+    pub fn netrc(&mut self) -> Result<&Netrc> {
+        match self.netrc {
+            Some(ref netrc) => Ok(netrc),
+            None => Ok(self.netrc.insert(Netrc::from_reader_with_options(
+                &mut self.buffer,
+                &self.options,
+            )?)),
         }
     }
 }
@@ -103,6 +237,8 @@ impl<R: Read> NetrcParser<R> {
 mod tests {
     use std::io::BufReader;
 
+    use crate::NetrcError;
+
     use super::*;
 
     const COM: &str = "example.com";
@@ -110,6 +246,8 @@ mod tests {
     const UNI: &str = "xn--9ca.com";
     const IP1: &str = "1.1.1.1";
     const IP2: &str = "2.2.2.2.";
+    const IP6: &str = "[::1]";
+    const IP6_LINK: &str = "[fe80::1]";
 
     #[test]
     fn parse_simple_config() {
@@ -239,6 +377,45 @@ mod tests {
         notfound(WEIRD_IP, COM);
     }
 
+    #[test]
+    fn parse_with_ipv6() {
+        const WITH_IPV6: &str = "
+            machine ::1 login us password pa
+            machine [fe80::1] login link password local
+        ";
+        found(WITH_IPV6, IP6, "us", "pa");
+        found(WITH_IPV6, IP6_LINK, "link", "local");
+        notfound(WITH_IPV6, "[::2]");
+        notfound(WITH_IPV6, IP1);
+    }
+
+    #[test]
+    fn parse_weird_ipv6() {
+        const WEIRD_IPV6: &str = "
+            machine 0:0:0:0:0:0:0:1 login us password pa
+            machine FE80:0::1 login link password local
+            machine ::ffff:1.1.1.1 login mapped password v4
+        ";
+        found(WEIRD_IPV6, IP6, "us", "pa");
+        found(WEIRD_IPV6, IP6_LINK, "link", "local");
+        found(WEIRD_IPV6, "[::ffff:101:101]", "mapped", "v4");
+        notfound(WEIRD_IPV6, IP1);
+    }
+
+    #[test]
+    fn parse_opaque_machine() {
+        const OPAQUE: &str = "
+            machine host_with_underscore:1234 login us password pa
+        ";
+        let entry = NetrcParser::new(OPAQUE.as_bytes())
+            .entry_for_name("host_with_underscore:1234")
+            .unwrap()
+            .expect("Didn't find entry");
+
+        assert_eq!(entry.password(), "pa");
+        notfound(OPAQUE, COM);
+    }
+
     #[test]
     fn parse_malformed_config() {
         const MALFORMED: &str = "
@@ -265,6 +442,50 @@ mod tests {
         found(OCTOTHORPE_IN_VALUE, COM, "#!@$", "pass");
     }
 
+    #[test]
+    fn parse_comment_without_space() {
+        const NO_SPACE: &str = "
+            #machine example.com login user password pass
+            machine example.org login lo password pa
+        ";
+        notfound(NO_SPACE, COM);
+        found(NO_SPACE, ORG, "lo", "pa");
+    }
+
+    #[test]
+    fn parse_trailing_comment() {
+        const TRAILING: &str = "
+            machine example.com # production
+            login user # the deploy user
+            password pass #rotated yearly
+            machine example.org login lo password pa
+        ";
+        found(TRAILING, COM, "user", "pass");
+        found(TRAILING, ORG, "lo", "pa");
+    }
+
+    #[test]
+    fn parse_comment_at_end_of_input() {
+        const COMMENT_AT_EOF: &str = "machine example.com login user password pass # no newline";
+        found(COMMENT_AT_EOF, COM, "user", "pass");
+    }
+
+    #[test]
+    fn parse_octothorpe_inside_value() {
+        const OCTOTHORPE_INSIDE_VALUE: &str = "
+            machine example.com login us#er password #pass#
+        ";
+        found(OCTOTHORPE_INSIDE_VALUE, COM, "us#er", "#pass#");
+    }
+
+    #[test]
+    fn parse_octothorpe_as_value() {
+        const OCTOTHORPE_AS_VALUE: &str = "
+            machine example.com login # password pass
+        ";
+        found(OCTOTHORPE_AS_VALUE, COM, "#", "pass");
+    }
+
     #[test]
     fn parse_sudden_end() {
         const SUDDEN_END: &str = "
@@ -349,6 +570,210 @@ mod tests {
         notfound(STRANGE_CHARACTERS, COM);
     }
 
+    #[test]
+    fn parse_values_starting_with_keywords() {
+        const KEYWORD_PREFIXES: &str = "
+            machine example.com login loginbot password passwordless-ci
+            machine defaulthost.com login machinery password accountant
+            machine example.org login defaults password macdefs account accounts
+        ";
+        found(KEYWORD_PREFIXES, COM, "loginbot", "passwordless-ci");
+        found(
+            KEYWORD_PREFIXES,
+            "defaulthost.com",
+            "machinery",
+            "accountant",
+        );
+        found(KEYWORD_PREFIXES, ORG, "defaults", "macdefs");
+    }
+
+    #[test]
+    fn parse_keywords_as_values() {
+        const KEYWORD_VALUES: &str = "
+            machine example.com login password password login
+        ";
+        found(KEYWORD_VALUES, COM, "password", "login");
+    }
+
+    #[test]
+    fn parse_keyword_prefix_in_keyword_position() {
+        const KEYWORD_PREFIX_TEXT: &str = "
+            machine example.com loginx user password pass
+        ";
+        notfound(KEYWORD_PREFIX_TEXT, COM);
+    }
+
+    #[test]
+    fn parse_duplicate_machines() {
+        const DUPLICATES: &str = "
+            machine example.com login first password one
+            machine example.com login second password two
+            machine example.com login incomplete
+        ";
+        found(DUPLICATES, COM, "first", "one");
+
+        let entries = NetrcParser::new(DUPLICATES.as_bytes())
+            .entries_for_host(&Host::parse(COM).unwrap())
+            .unwrap();
+        assert_eq!(
+            entries,
+            [
+                ValidatedEntry::new(Some("first".to_string()), "one"),
+                ValidatedEntry::new(Some("second".to_string()), "two"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_login_lookup() {
+        const BOTS: &str = "
+            machine example.com login alice password wonderland
+            machine example.com login bob password builder
+        ";
+        let mut parser = NetrcParser::new(BOTS.as_bytes());
+        let host = Host::parse(COM).unwrap();
+
+        let bob = parser.entry_for_host_and_login(&host, Some("bob")).unwrap();
+        assert_eq!(bob.unwrap().password(), "builder");
+        let first = parser.entry_for_host_and_login(&host, None).unwrap();
+        assert_eq!(first.unwrap().password(), "wonderland");
+        let carol = parser.entry_for_host_and_login(&host, Some("carol"));
+        assert!(carol.unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_url_lookup() {
+        const BOTS: &str = "
+            machine example.com login alice password wonderland
+            machine example.com login bob password builder
+        ";
+        let mut parser = NetrcParser::new(BOTS.as_bytes());
+        let mut lookup = |url: &str| parser.entry_for_url(&Url::parse(url).unwrap());
+
+        let first = lookup("https://example.com/repo.git").unwrap().unwrap();
+        assert_eq!(first.password(), "wonderland");
+        let bob = lookup("https://bob@example.com/repo.git").unwrap().unwrap();
+        assert_eq!(bob.password(), "builder");
+        assert!(lookup("https://example.org/").unwrap().is_none());
+        assert!(matches!(
+            lookup("file:///etc/passwd"),
+            Err(NetrcError::MissingHost { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_port_lookup() {
+        const PORTS: &str = "
+            machine example.com port 8443 login alt password alternative
+            machine example.com login user password pass
+        ";
+        let mut parser = NetrcParser::new(PORTS.as_bytes());
+        let host = Host::parse(COM).unwrap();
+
+        let alt = parser.entry_for_host_and_port(&host, 8443).unwrap();
+        assert_eq!(alt.unwrap().password(), "alternative");
+        let other = parser.entry_for_host_and_port(&host, 443).unwrap();
+        assert_eq!(other.unwrap().password(), "pass");
+    }
+
+    #[test]
+    fn validate_with_presets() {
+        const ACCOUNT: &str = "
+            machine example.com account acct password pass
+            machine example.org login user
+        ";
+        let entry = |policy, host| {
+            NetrcParser::with_policy(ACCOUNT.as_bytes(), policy)
+                .entry_for_host(&Host::parse(host).unwrap())
+                .unwrap()
+        };
+
+        let curl = entry(ValidationPolicy::curl(), COM).unwrap();
+        assert_eq!(curl.login().map(String::as_str), Some("acct"));
+        assert_eq!(curl.account().map(String::as_str), Some("acct"));
+        assert!(entry(ValidationPolicy::curl(), ORG).is_none());
+
+        let python = entry(ValidationPolicy::python(), COM).unwrap();
+        assert_eq!(python.login(), None);
+        assert_eq!(python.account().map(String::as_str), Some("acct"));
+        assert!(entry(ValidationPolicy::python(), ORG).is_none());
+
+        let inetutils = entry(ValidationPolicy::inetutils(), ORG).unwrap();
+        assert_eq!(inetutils.login().map(String::as_str), Some("user"));
+        assert_eq!(inetutils.password(), "");
+        assert!(!inetutils.has_password());
+        assert!(entry(ValidationPolicy::inetutils(), COM)
+            .unwrap()
+            .has_password());
+
+        let empty = NetrcParser::with_policy(
+            "machine example.com password \"\"".as_bytes(),
+            ValidationPolicy::inetutils(),
+        )
+        .entry_for_host(&Host::parse(COM).unwrap())
+        .unwrap()
+        .unwrap();
+        assert_eq!(empty.password(), "");
+        assert!(empty.has_password());
+    }
+
+    #[test]
+    fn validate_requiring_login() {
+        const TOKEN: &str = "
+            machine example.com password token
+        ";
+        let policy = ValidationPolicy::new().require_login(true);
+        let entry = NetrcParser::with_policy(TOKEN.as_bytes(), policy)
+            .entry_for_host(&Host::parse(COM).unwrap());
+
+        assert!(entry.unwrap().is_none());
+        found(TOKEN, COM, None, "token");
+    }
+
+    #[test]
+    fn validate_with_fallback_from_incomplete() {
+        const INCOMPLETE: &str = "
+            machine example.com login user
+            machine example.com login other password second
+            machine example.org login foo
+            default login anonymous password guest
+        ";
+        let policy = ValidationPolicy::new().fallback_from_incomplete(true);
+        let mut parser = NetrcParser::with_policy(INCOMPLETE.as_bytes(), policy);
+
+        let com = parser.entry_for_host(&Host::parse(COM).unwrap()).unwrap();
+        assert_eq!(
+            com,
+            Some(ValidatedEntry::new("other".to_string(), "second"))
+        );
+        let org = parser.entry_for_host(&Host::parse(ORG).unwrap()).unwrap();
+        assert_eq!(
+            org,
+            Some(ValidatedEntry::new("anonymous".to_string(), "guest"))
+        );
+
+        notfound(INCOMPLETE, COM);
+        notfound(INCOMPLETE, ORG);
+    }
+
+    #[test]
+    fn validate_merging_default() {
+        const MERGE: &str = "
+            machine example.com login user
+            default login anonymous password guest account acct
+        ";
+        let policy = ValidationPolicy::new().merge_default(true);
+        let entry = NetrcParser::with_policy(MERGE.as_bytes(), policy)
+            .entry_for_host(&Host::parse(COM).unwrap())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(entry.login().map(String::as_str), Some("user"));
+        assert_eq!(entry.password(), "guest");
+        assert_eq!(entry.account().map(String::as_str), Some("acct"));
+        notfound(MERGE, COM);
+    }
+
     #[track_caller]
     fn found(netrc: &str, host: &str, login: impl Into<Option<&'static str>>, password: &str) {
         let entry = NetrcParser::new(BufReader::new(netrc.as_bytes()))
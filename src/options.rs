@@ -0,0 +1,257 @@
+/// Options controlling how a netrc document is parsed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    strict: bool,
+    wildcards: bool,
+    cidr: bool,
+    host_matching: HostMatchOptions,
+    validation: ValidationPolicy,
+}
+
+impl ParseOptions {
+    /// Create the default options, which parse leniently like curl does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable strict mode. In strict mode any malformed input is reported as a
+    /// [crate::error::NetrcError::Syntax] error instead of silently leaving out the affected
+    /// entries.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Whether strict mode is enabled.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Enable or disable wildcard machine names. When enabled, a `machine *.mirrors.corp.example`
+    /// entry applies to every subdomain of `mirrors.corp.example` that has no entry of its own,
+    /// with the longest matching wildcard taking precedence. When disabled, such names are
+    /// literal names like any other.
+    pub fn wildcards(mut self, wildcards: bool) -> Self {
+        self.wildcards = wildcards;
+        self
+    }
+
+    /// Whether wildcard machine names are enabled.
+    pub fn allows_wildcards(&self) -> bool {
+        self.wildcards
+    }
+
+    /// Enable or disable network machine names in CIDR notation, for both IPv4 and IPv6. When
+    /// enabled, a `machine 10.20.0.0/16` entry applies to every address in the network that has
+    /// no entry of its own, with the longest matching prefix taking precedence. When disabled,
+    /// such names are not valid hosts.
+    pub fn cidr(mut self, cidr: bool) -> Self {
+        self.cidr = cidr;
+        self
+    }
+
+    /// Whether network machine names are enabled.
+    pub fn allows_cidr(&self) -> bool {
+        self.cidr
+    }
+
+    /// Set how machine names are compared to the hosts that are looked up.
+    pub fn host_matching(mut self, host_matching: HostMatchOptions) -> Self {
+        self.host_matching = host_matching;
+        self
+    }
+
+    /// How machine names are compared to the hosts that are looked up.
+    pub fn host_match_options(&self) -> &HostMatchOptions {
+        &self.host_matching
+    }
+
+    /// Set the rules an entry must follow to be returned by the validated lookups.
+    pub fn validation(mut self, validation: ValidationPolicy) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// The rules an entry must follow to be returned by the validated lookups.
+    pub fn validation_policy(&self) -> &ValidationPolicy {
+        &self.validation
+    }
+}
+
+/// Options controlling how machine names are compared to the hosts that are looked up. They are
+/// applied the same way to the machine names in the input and to the hosts that are looked up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostMatchOptions {
+    ignore_trailing_dot: bool,
+    idna: bool,
+    case_insensitive: bool,
+}
+
+impl Default for HostMatchOptions {
+    fn default() -> Self {
+        Self {
+            ignore_trailing_dot: false,
+            idna: true,
+            case_insensitive: false,
+        }
+    }
+}
+
+impl HostMatchOptions {
+    /// Create the default options, which compare hosts like [url::Host::parse] does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat a fully qualified name with a trailing dot, like `example.com.`, the same as the
+    /// name without it. Disabled by default.
+    pub fn ignore_trailing_dot(mut self, ignore_trailing_dot: bool) -> Self {
+        self.ignore_trailing_dot = ignore_trailing_dot;
+        self
+    }
+
+    /// Whether a trailing dot is ignored.
+    pub fn ignores_trailing_dot(&self) -> bool {
+        self.ignore_trailing_dot
+    }
+
+    /// Enable or disable IDNA processing of machine names. When enabled, a Unicode name like
+    /// `é.com` matches its punycode form `xn--9ca.com`. When disabled, names containing
+    /// non-ASCII characters are kept as written and only match the exact same name. Enabled by
+    /// default.
+    pub fn idna(mut self, idna: bool) -> Self {
+        self.idna = idna;
+        self
+    }
+
+    /// Whether IDNA processing is enabled.
+    pub fn uses_idna(&self) -> bool {
+        self.idna
+    }
+
+    /// Compare machine names that are not valid hosts case-insensitively. Valid host names are
+    /// always compared case-insensitively. Disabled by default.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Whether machine names that are not valid hosts are compared case-insensitively.
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+}
+
+/// The rules an entry must follow to be returned as a [crate::netrc_parser::ValidatedEntry]. The
+/// default is [ValidationPolicy::curl].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationPolicy {
+    require_password: bool,
+    require_login: bool,
+    account_as_login: bool,
+    fallback_from_incomplete: bool,
+    merge_default: bool,
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        Self::curl()
+    }
+}
+
+impl ValidationPolicy {
+    /// Create the default policy, see [ValidationPolicy::curl].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rules curl follows: an entry must have a password and may have a login, the account
+    /// is used when the login is missing, and an incomplete entry neither falls back to nor takes
+    /// fields from the default entry.
+    pub fn curl() -> Self {
+        Self {
+            require_password: true,
+            require_login: false,
+            account_as_login: true,
+            fallback_from_incomplete: false,
+            merge_default: false,
+        }
+    }
+
+    /// The rules the Python `netrc` module follows: like curl, except that the login and the
+    /// account are kept separate.
+    pub fn python() -> Self {
+        Self::curl().account_as_login(false)
+    }
+
+    /// Rules modelled on the inetutils ftp client, which accepts entries with any of the fields
+    /// missing and keeps the login and the account separate. Where the client prompts for a
+    /// missing password, the validated entry has an empty password and
+    /// [crate::netrc_parser::ValidatedEntry::has_password] returns `false`.
+    pub fn inetutils() -> Self {
+        Self::python().require_password(false)
+    }
+
+    /// Require entries to have a password. Entries without one are invalid when enabled. When
+    /// disabled they get an empty password, and
+    /// [crate::netrc_parser::ValidatedEntry::has_password] tells them apart from entries with an
+    /// empty `password ""`.
+    pub fn require_password(mut self, require_password: bool) -> Self {
+        self.require_password = require_password;
+        self
+    }
+
+    /// Whether entries must have a password.
+    pub fn requires_password(&self) -> bool {
+        self.require_password
+    }
+
+    /// Require entries to have a login, after the account is used in its place when enabled with
+    /// [ValidationPolicy::account_as_login].
+    pub fn require_login(mut self, require_login: bool) -> Self {
+        self.require_login = require_login;
+        self
+    }
+
+    /// Whether entries must have a login.
+    pub fn requires_login(&self) -> bool {
+        self.require_login
+    }
+
+    /// Use the account as the login of entries without a login. When disabled, the account is
+    /// only available through [crate::netrc_parser::ValidatedEntry::account].
+    pub fn account_as_login(mut self, account_as_login: bool) -> Self {
+        self.account_as_login = account_as_login;
+        self
+    }
+
+    /// Whether the account is used as the login of entries without a login.
+    pub fn uses_account_as_login(&self) -> bool {
+        self.account_as_login
+    }
+
+    /// Skip invalid entries of a host when looking it up, using the next valid entry of the host
+    /// or the default entry instead. When disabled, the lookup finds nothing if the first entry
+    /// of the host is invalid.
+    pub fn fallback_from_incomplete(mut self, fallback_from_incomplete: bool) -> Self {
+        self.fallback_from_incomplete = fallback_from_incomplete;
+        self
+    }
+
+    /// Whether invalid entries are skipped when looking up a host.
+    pub fn falls_back_from_incomplete(&self) -> bool {
+        self.fallback_from_incomplete
+    }
+
+    /// Fill the fields missing from an entry with those of the default entry, before the entry
+    /// is validated.
+    pub fn merge_default(mut self, merge_default: bool) -> Self {
+        self.merge_default = merge_default;
+        self
+    }
+
+    /// Whether missing fields are taken from the default entry.
+    pub fn merges_default(&self) -> bool {
+        self.merge_default
+    }
+}
@@ -1,20 +1,29 @@
-use std::collections::HashMap;
-
+use std::{borrow::Cow, fmt};
+
+use super::{
+    error::{ParseError, ParseErrorKind},
+    host::{parse_key, parse_network, parse_wildcard, HostKey},
+    macdef::Macro,
+    options::ParseOptions,
+    raw_netrc_parser::RawEntry,
+    span::{LineIndex, Span},
+};
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until, take_while, take_while1},
-    combinator::map,
-    sequence::tuple,
+    bytes::complete::{tag, take_till, take_while, take_while1},
+    character::complete::{char, satisfy},
+    combinator::{map, not},
+    sequence::{preceded, terminated, tuple},
     IResult,
 };
-use url::Host;
-
-use super::raw_netrc_parser::RawEntry;
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub(crate) struct NetrcConfig {
-    pub(crate) entries: HashMap<Host, RawEntry>,
+    /// Machine entries in the order they appear in the input, including duplicate hosts.
+    pub(crate) entries: Vec<(HostKey, RawEntry)>,
     pub(crate) default: Option<RawEntry>,
+    /// Spans of every password value in the input, including those of ignored entries.
+    pub(crate) password_spans: Vec<Span>,
 }
 
 #[derive(Debug)]
@@ -24,90 +33,213 @@ enum Token {
     Login,
     Password,
     Account,
-    MacDef(String, String),
+    Port,
+    Protocol,
+    Path,
+    MacDef(MacDef),
     Comment(String),
     Text(String),
+    /// A double quoted value with its escape sequences resolved. Quoted values are never keywords.
+    Quoted {
+        value: String,
+        terminated: bool,
+    },
 }
 
-impl ToString for Token {
-    fn to_string(&self) -> String {
+#[derive(Debug)]
+struct MacDef {
+    name: String,
+    lines: Vec<String>,
+    terminated: bool,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Token::Machine => "machine".to_string(),
-            Token::Default => "default".to_string(),
-            Token::Login => "login".to_string(),
-            Token::Password => "password".to_string(),
-            Token::Account => "account".to_string(),
-            Token::MacDef(name, content) => format!("macdef {name} {content}"),
-            Token::Comment(comment) => format!("# {comment}"),
-            Token::Text(text) => text.to_string(),
+            Token::Machine => write!(f, "machine"),
+            Token::Default => write!(f, "default"),
+            Token::Login => write!(f, "login"),
+            Token::Password => write!(f, "password"),
+            Token::Account => write!(f, "account"),
+            Token::Port => write!(f, "port"),
+            Token::Protocol => write!(f, "protocol"),
+            Token::Path => write!(f, "path"),
+            Token::MacDef(MacDef { name, .. }) => write!(f, "macdef {name}"),
+            Token::Comment(comment) => write!(f, "# {comment}"),
+            Token::Text(text) => write!(f, "{text}"),
+            Token::Quoted { value, .. } => write!(f, "{value}"),
         }
     }
 }
 
-pub(crate) fn parse_config(input: &str) -> NetrcConfig {
+/// A token together with the region of the input it was parsed from.
+#[derive(Debug)]
+struct Spanned {
+    token: Token,
+    span: Span,
+}
+
+/// Parse the input into a config. Parsing is lenient: problems are collected into the returned
+/// errors and the affected entries are left out, but parsing continues with the rest of the input.
+pub(crate) fn parse_config(input: &str, options: &ParseOptions) -> (NetrcConfig, Vec<ParseError>) {
     let tokens = tokenize(input);
 
-    let mut entries = HashMap::new();
+    let mut errors = vec![];
+    let mut password_spans = vec![];
+
+    let mut entries = vec![];
     let mut default = RawEntry::default();
+    let mut default_valid = true;
 
-    let mut active_machine: Option<Host> = None;
+    let mut active_machine: Option<HostKey> = None;
     let mut active_entry = RawEntry::default();
 
     let mut i = 0;
     let mut in_default = false;
+    // Whether the previous token was a password value or text following one
+    let mut after_password = false;
 
     while let Some(next) = tokens.get(i) {
-        match next {
+        let follows_password = std::mem::take(&mut after_password);
+
+        match &next.token {
             Token::Machine => {
                 i += 1;
                 in_default = false;
 
                 if let Some(ref machine) = active_machine {
-                    entries.insert(machine.clone(), active_entry.clone());
+                    entries.push((machine.clone(), active_entry.clone()));
                 }
 
-                if let Some(machine) = tokens.get(i) {
-                    active_machine = Host::parse(&machine.to_string()).ok();
-                    active_entry = RawEntry::default()
-                }
+                active_entry = RawEntry::default();
+                active_machine = value(&tokens, i, &mut errors).map(|(machine, span)| {
+                    active_entry.span = next.span.to(span);
+
+                    // Names that are not valid hosts are reported, but kept to be matched as
+                    // plain strings
+                    let host_matching = options.host_match_options();
+                    let pattern = options
+                        .allows_wildcards()
+                        .then(|| parse_wildcard(&machine, host_matching))
+                        .flatten()
+                        .or_else(|| {
+                            options
+                                .allows_cidr()
+                                .then(|| parse_network(&machine))
+                                .flatten()
+                        });
+
+                    match pattern.map_or_else(|| parse_key(&machine, host_matching), Ok) {
+                        Ok(key) => key,
+                        Err(source) => {
+                            errors.push(ParseError::new(
+                                ParseErrorKind::InvalidHost {
+                                    host: machine.clone(),
+                                    source,
+                                },
+                                span,
+                            ));
+                            HostKey::Opaque(machine)
+                        }
+                    }
+                });
             }
             Token::Default => {
                 in_default = true;
+                default.span = next.span;
             }
-            Token::Login => {
+            Token::Login
+            | Token::Password
+            | Token::Account
+            | Token::Port
+            | Token::Protocol
+            | Token::Path => {
                 i += 1;
 
-                if in_default {
-                    default.login = tokens.get(i).map(Token::to_string);
+                let entry = if in_default {
+                    &mut default
                 } else {
-                    active_entry.login = tokens.get(i).map(Token::to_string);
+                    &mut active_entry
+                };
+                let (value, span) = value(&tokens, i, &mut errors).unzip();
+
+                if let Some(span) = span {
+                    entry.span = entry.span.to(span);
                 }
-            }
-            Token::Password => {
-                i += 1;
 
-                if in_default {
-                    default.password = tokens.get(i).map(Token::to_string);
-                } else {
-                    active_entry.password = tokens.get(i).map(Token::to_string);
+                match next.token {
+                    Token::Login => (entry.login, entry.login_span) = (value, span),
+                    Token::Password => {
+                        after_password = span.is_some();
+                        password_spans.extend(span);
+                        (entry.password, entry.password_span) = (value, span);
+                    }
+                    Token::Account => (entry.account, entry.account_span) = (value, span),
+                    // An entry with an invalid port is left out rather than matching every port
+                    Token::Port => {
+                        entry.port = value.zip(span).and_then(|(port, span)| {
+                            let parsed = port.parse().ok();
+                            if parsed.is_none() {
+                                errors
+                                    .push(ParseError::new(ParseErrorKind::InvalidPort(port), span));
+                                if !in_default {
+                                    active_machine = None;
+                                }
+                            }
+                            parsed
+                        });
+                        entry.port_span = entry.port.and(span);
+                    }
+                    Token::Protocol => (entry.protocol, entry.protocol_span) = (value, span),
+                    _ => (entry.path, entry.path_span) = (value, span),
                 }
             }
-            Token::Account => {
-                i += 1;
+            // Macros belong to the entry they are defined in, macros outside of any entry are
+            // ignored
+            Token::MacDef(MacDef {
+                name,
+                lines,
+                terminated,
+            }) => {
+                if !terminated {
+                    errors.push(ParseError::new(
+                        ParseErrorKind::UnterminatedMacro(name.clone()),
+                        next.span,
+                    ));
+                }
 
-                if in_default {
-                    default.account = tokens.get(i).map(Token::to_string);
+                let entry = if in_default {
+                    Some(&mut default)
                 } else {
-                    active_entry.account = tokens.get(i).map(Token::to_string);
+                    active_machine.as_ref().map(|_| &mut active_entry)
+                };
+
+                if let Some(entry) = entry {
+                    entry.span = entry.span.to(next.span);
+                    entry
+                        .macros
+                        .push(Macro::new(name.clone(), lines.clone(), next.span));
                 }
             }
-            // Macros should be ignored
-            Token::MacDef(..) => (),
             // Comments should be ignored
             Token::Comment(_) => (),
-            // Text here should invalidate the whole entry
-            Token::Text(_) => {
-                active_machine = None;
+            // Text here should invalidate the whole entry. Text after a password is likely part of
+            // an unquoted password, so it is masked like one
+            Token::Text(text) | Token::Quoted { value: text, .. } => {
+                let kind = if follows_password {
+                    after_password = true;
+                    password_spans.push(next.span);
+                    ParseErrorKind::UnquotedPassword
+                } else {
+                    ParseErrorKind::UnexpectedToken(text.clone())
+                };
+
+                errors.push(ParseError::new(kind, next.span));
+                if in_default {
+                    default_valid = false;
+                } else {
+                    active_machine = None;
+                }
             }
         }
 
@@ -115,29 +247,86 @@ pub(crate) fn parse_config(input: &str) -> NetrcConfig {
     }
 
     if let Some(machine) = active_machine {
-        entries.insert(machine, active_entry);
+        entries.push((machine, active_entry));
     }
 
-    NetrcConfig {
+    let config = NetrcConfig {
         entries,
-        default: if default == RawEntry::default() {
-            None
-        } else {
+        default: if default_valid && default.has_values() {
             Some(default)
+        } else {
+            None
         },
+        password_spans,
+    };
+
+    (config, errors)
+}
+
+/// The value at index `i`, following the keyword before it. A missing value is reported at the
+/// keyword.
+fn value(tokens: &[Spanned], i: usize, errors: &mut Vec<ParseError>) -> Option<(String, Span)> {
+    match tokens.get(i) {
+        Some(value) => {
+            if let Token::Quoted {
+                terminated: false, ..
+            } = value.token
+            {
+                errors.push(ParseError::new(
+                    ParseErrorKind::UnterminatedQuote,
+                    value.span,
+                ));
+            }
+
+            Some((value.token.to_string(), value.span))
+        }
+        None => {
+            let keyword = &tokens[i - 1];
+            errors.push(ParseError::new(
+                ParseErrorKind::MissingValue(keyword.token.to_string()),
+                keyword.span,
+            ));
+
+            None
+        }
     }
 }
 
-fn tokenize(input: &str) -> Vec<Token> {
-    let mut input = input;
+fn tokenize(input: &str) -> Vec<Spanned> {
+    let index = LineIndex::new(input);
+
+    let mut rest = input;
     let mut tokens = vec![];
+    // Whether the previous token was a keyword taking a value
+    let mut expect_value = false;
 
-    while let Ok((rest, (_, token))) = tuple((drop_whitespace, token))(input) {
-        input = rest;
+    while let Ok((trimmed, ())) = drop_whitespace(rest) {
+        let parsed = if expect_value {
+            value_token(trimmed)
+        } else {
+            token(trimmed)
+        };
+        let Ok((next, token)) = parsed else {
+            break;
+        };
+
+        let span = index.span(input.len() - trimmed.len(), input.len() - next.len());
+        rest = next;
+
+        expect_value = matches!(
+            token,
+            Token::Machine
+                | Token::Login
+                | Token::Password
+                | Token::Account
+                | Token::Port
+                | Token::Protocol
+                | Token::Path
+        );
 
         match token {
             Token::Comment(_) => (),
-            token => tokens.push(token),
+            token => tokens.push(Spanned { token, span }),
         }
     }
 
@@ -146,34 +335,60 @@ fn tokenize(input: &str) -> Vec<Token> {
 
 fn token(input: &str) -> IResult<&str, Token> {
     alt((
-        machine, login, password, account, default, comment, macdef, text,
+        machine, login, password, account, port, protocol, path, default, comment, macdef, quoted,
+        text,
     ))(input)
 }
 
+/// A token following a keyword taking a value. Values are never keywords or comments, so a value
+/// like `#hash` or `login` is kept as is.
+fn value_token(input: &str) -> IResult<&str, Token> {
+    alt((quoted, text))(input)
+}
+
 fn machine(input: &str) -> IResult<&str, Token> {
-    map(tag("machine"), |_| Token::Machine)(input)
+    map(keyword("machine"), |_| Token::Machine)(input)
 }
 
 fn login(input: &str) -> IResult<&str, Token> {
-    map(tag("login"), |_| Token::Login)(input)
+    map(keyword("login"), |_| Token::Login)(input)
 }
 
 fn password(input: &str) -> IResult<&str, Token> {
-    map(tag("password"), |_| Token::Password)(input)
+    map(keyword("password"), |_| Token::Password)(input)
 }
 
 fn account(input: &str) -> IResult<&str, Token> {
-    map(tag("account"), |_| Token::Account)(input)
+    map(keyword("account"), |_| Token::Account)(input)
+}
+
+fn port(input: &str) -> IResult<&str, Token> {
+    map(keyword("port"), |_| Token::Port)(input)
+}
+
+fn protocol(input: &str) -> IResult<&str, Token> {
+    map(keyword("protocol"), |_| Token::Protocol)(input)
+}
+
+fn path(input: &str) -> IResult<&str, Token> {
+    map(keyword("path"), |_| Token::Path)(input)
 }
 
 fn default(input: &str) -> IResult<&str, Token> {
-    map(tag("default"), |_| Token::Default)(input)
+    map(keyword("default"), |_| Token::Default)(input)
 }
 
+/// A keyword, which must be a whole word. Words merely starting with a keyword are text.
+fn keyword<'a>(name: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    terminated(tag(name), not(satisfy(|c: char| !c.is_whitespace())))
+}
+
+/// A comment, starting at a `#` where a keyword is expected and running until the end of the
+/// line.
 fn comment(input: &str) -> IResult<&str, Token> {
     map(
-        tuple((tag("# "), take_until("\n"))),
-        |(_, comment): (_, &str)| Token::Comment(comment.to_string()),
+        preceded(char('#'), take_till(|c| c == '\n')),
+        |comment: &str| Token::Comment(comment.trim().to_string()),
     )(input)
 }
 
@@ -181,18 +396,101 @@ fn text(input: &str) -> IResult<&str, Token> {
     map(word, |text| Token::Text(text.to_string()))(input)
 }
 
+/// A value between double quotes, as supported by curl. The escape sequences `\"`, `\\`, `\n`,
+/// `\r` and `\t` are resolved, any other escaped character stands for itself. A value missing its
+/// closing quote ends at the end of the line.
+fn quoted(input: &str) -> IResult<&str, Token> {
+    let (content, _) = tag("\"")(input)?;
+
+    let mut value = String::new();
+    let mut chars = content.char_indices();
+
+    let (rest, terminated) = loop {
+        match chars.next() {
+            Some((i, '"')) => break (&content[i + 1..], true),
+            Some((i, '\n')) | Some((i, '\r')) => break (&content[i..], false),
+            Some((_, '\\')) => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, 't')) => value.push('\t'),
+                Some((i, '\n')) | Some((i, '\r')) => break (&content[i..], false),
+                Some((_, c)) => value.push(c),
+                None => break ("", false),
+            },
+            Some((_, c)) => value.push(c),
+            None => break ("", false),
+        }
+    };
+
+    Ok((rest, Token::Quoted { value, terminated }))
+}
+
+/// Quote a value when writing it out, if it could not be read back as is. This is the inverse of
+/// the parsing done by [quoted].
+pub(crate) fn quote(value: &str) -> Cow<'_, str> {
+    let needs_quotes = value.is_empty()
+        || value.starts_with('#')
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\\');
+
+    if !needs_quotes {
+        return Cow::Borrowed(value);
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
 fn macdef(input: &str) -> IResult<&str, Token> {
     map(
-        tuple((
-            tag("macdef"),
-            drop_whitespace,
-            word,
-            alt((take_until("\n\n"), take_while(|_| true))),
-        )),
-        |(_, _, name, content)| Token::MacDef(name.to_string(), content.to_string()),
+        tuple((keyword("macdef"), drop_whitespace, word, macro_body)),
+        |(_, _, name, (lines, terminated))| {
+            Token::MacDef(MacDef {
+                name: name.to_string(),
+                lines,
+                terminated,
+            })
+        },
     )(input)
 }
 
+/// The body of a macro: the lines after the line holding its name, up to the first blank line. A
+/// body that is not terminated by a blank line runs until the end of the input.
+fn macro_body(input: &str) -> IResult<&str, (Vec<String>, bool)> {
+    let mut lines = vec![];
+    // The rest of the line holding the name is not part of the body
+    let mut rest = input.find('\n').map_or("", |newline| &input[newline + 1..]);
+
+    while !rest.is_empty() {
+        let (line, next) = rest.split_once('\n').unwrap_or((rest, ""));
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        if line.is_empty() {
+            return Ok((rest, (lines, true)));
+        }
+
+        lines.push(line.to_string());
+        rest = next;
+    }
+
+    Ok((rest, (lines, false)))
+}
+
 fn drop_whitespace(input: &str) -> IResult<&str, ()> {
     map(take_while(|c: char| c.is_whitespace()), |_| ())(input)
 }
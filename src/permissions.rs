@@ -0,0 +1,215 @@
+use std::{fmt, fs::File, io, path::Path, path::PathBuf};
+
+/// What to do when a netrc file is found to have insecure permissions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionPolicy {
+    /// Do not check the permissions of the file.
+    #[default]
+    Ignore,
+    /// Load the file, but record the violations. They are available through
+    /// [crate::netrc::Netrc::permission_warnings].
+    Warn,
+    /// Refuse to load the file, returning the first violation as an error.
+    Error,
+}
+
+/// A permission check a netrc file failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PermissionViolation {
+    /// The file contains passwords and can be accessed by the group or other users. Mirrors the
+    /// check done by the inetutils ftp client.
+    GroupOrWorldAccessible {
+        /// The checked file, with symlinks resolved.
+        path: PathBuf,
+        /// The permission bits of the file.
+        mode: u32,
+    },
+    /// The file is not owned by the current user.
+    WrongOwner {
+        /// The checked file, with symlinks resolved.
+        path: PathBuf,
+        /// The uid owning the file.
+        owner: u32,
+        /// The uid of the current user.
+        expected: u32,
+    },
+    /// The file is a symlink that is not owned by the current user, so someone else controls
+    /// which file is read.
+    SymlinkWrongOwner {
+        /// The symlink itself.
+        link: PathBuf,
+        /// The uid owning the symlink.
+        owner: u32,
+        /// The uid of the current user.
+        expected: u32,
+    },
+}
+
+impl fmt::Display for PermissionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PermissionViolation::GroupOrWorldAccessible { path, mode } => write!(
+                f,
+                "{} contains passwords but is accessible by other users (mode {:o})",
+                path.display(),
+                mode & 0o777
+            ),
+            PermissionViolation::WrongOwner {
+                path,
+                owner,
+                expected,
+            } => write!(
+                f,
+                "{} is owned by uid {owner} instead of uid {expected}",
+                path.display()
+            ),
+            PermissionViolation::SymlinkWrongOwner {
+                link,
+                owner,
+                expected,
+            } => write!(
+                f,
+                "symlink {} is owned by uid {owner} instead of uid {expected}",
+                link.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PermissionViolation {}
+
+/// Check the permissions of an opened netrc file. The metadata of the opened file is used, so the
+/// checked file is the one that is read even when the path is swapped out in between.
+#[cfg(unix)]
+pub(crate) fn check_permissions(
+    path: &Path,
+    file: &File,
+    has_password: bool,
+) -> io::Result<Vec<PermissionViolation>> {
+    use std::os::unix::fs::MetadataExt;
+
+    // SAFETY: getuid cannot fail and has no preconditions
+    let user = unsafe { libc::getuid() };
+
+    let mut violations = vec![];
+
+    let link = path.symlink_metadata()?;
+    let target = if link.file_type().is_symlink() {
+        if link.uid() != user {
+            violations.push(PermissionViolation::SymlinkWrongOwner {
+                link: path.to_path_buf(),
+                owner: link.uid(),
+                expected: user,
+            });
+        }
+
+        path.canonicalize()?
+    } else {
+        path.to_path_buf()
+    };
+
+    let metadata = file.metadata()?;
+    violations.extend(check_metadata(
+        &target,
+        metadata.mode(),
+        metadata.uid(),
+        user,
+        has_password,
+    ));
+
+    Ok(violations)
+}
+
+/// Permissions are only checked on unix-like systems.
+#[cfg(not(unix))]
+pub(crate) fn check_permissions(
+    _path: &Path,
+    _file: &File,
+    _has_password: bool,
+) -> io::Result<Vec<PermissionViolation>> {
+    Ok(vec![])
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+fn check_metadata(
+    path: &Path,
+    mode: u32,
+    owner: u32,
+    user: u32,
+    has_password: bool,
+) -> Vec<PermissionViolation> {
+    let mut violations = vec![];
+
+    if owner != user {
+        violations.push(PermissionViolation::WrongOwner {
+            path: path.to_path_buf(),
+            owner,
+            expected: user,
+        });
+    }
+
+    if has_password && mode & 0o077 != 0 {
+        violations.push(PermissionViolation::GroupOrWorldAccessible {
+            path: path.to_path_buf(),
+            mode,
+        });
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "/home/user/.netrc";
+
+    #[test]
+    fn private_file() {
+        assert_eq!(check_metadata(Path::new(PATH), 0o600, 1000, 1000, true), []);
+    }
+
+    #[test]
+    fn readable_without_password() {
+        assert_eq!(
+            check_metadata(Path::new(PATH), 0o644, 1000, 1000, false),
+            []
+        );
+    }
+
+    #[test]
+    fn readable_with_password() {
+        assert_eq!(
+            check_metadata(Path::new(PATH), 0o100640, 1000, 1000, true),
+            [PermissionViolation::GroupOrWorldAccessible {
+                path: PATH.into(),
+                mode: 0o100640
+            }]
+        );
+    }
+
+    #[test]
+    fn wrong_owner() {
+        assert_eq!(
+            check_metadata(Path::new(PATH), 0o600, 0, 1000, false),
+            [PermissionViolation::WrongOwner {
+                path: PATH.into(),
+                owner: 0,
+                expected: 1000
+            }]
+        );
+    }
+
+    #[test]
+    fn violation_message() {
+        let violation = PermissionViolation::GroupOrWorldAccessible {
+            path: PATH.into(),
+            mode: 0o100644,
+        };
+
+        assert_eq!(
+            violation.to_string(),
+            "/home/user/.netrc contains passwords but is accessible by other users (mode 644)"
+        );
+    }
+}
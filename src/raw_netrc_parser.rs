@@ -1,9 +1,8 @@
 use std::io::Read;
 
-use anyhow::Result;
 use url::Host;
 
-use crate::parser_combinator::{parse_config, NetrcConfig};
+use crate::{error::Result, macdef::Macro, netrc::Netrc, span::Span};
 
 /// A raw netrc entry which may contain values.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -11,6 +10,17 @@ pub struct RawEntry {
     pub(crate) login: Option<String>,
     pub(crate) password: Option<String>,
     pub(crate) account: Option<String>,
+    pub(crate) port: Option<u16>,
+    pub(crate) protocol: Option<String>,
+    pub(crate) path: Option<String>,
+    pub(crate) macros: Vec<Macro>,
+    pub(crate) span: Span,
+    pub(crate) login_span: Option<Span>,
+    pub(crate) password_span: Option<Span>,
+    pub(crate) account_span: Option<Span>,
+    pub(crate) port_span: Option<Span>,
+    pub(crate) protocol_span: Option<Span>,
+    pub(crate) path_span: Option<Span>,
 }
 
 /// A raw netrc entry containing some values.
@@ -29,6 +39,84 @@ impl RawEntry {
     pub fn account(&self) -> Option<&String> {
         self.account.as_ref()
     }
+
+    /// Get the port the entry is restricted to, set with the `port` keyword. Entries without a
+    /// port apply to every port of the host, entries with one only to lookups for that port.
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// Get the protocol value for the entry, set with the `protocol` keyword.
+    pub fn protocol(&self) -> Option<&String> {
+        self.protocol.as_ref()
+    }
+
+    /// Get the URL path the entry is restricted to, set with the `path` keyword. The entry applies
+    /// to URLs whose path starts with the value, see [crate::netrc::Netrc::entry_for_url].
+    pub fn path(&self) -> Option<&String> {
+        self.path.as_ref()
+    }
+
+    /// Get the macros defined in the entry, in the order they appear in the input.
+    pub fn macros(&self) -> &[Macro] {
+        &self.macros
+    }
+
+    /// Get the first macro in the entry with the given name.
+    pub fn macro_by_name(&self, name: &str) -> Option<&Macro> {
+        self.macros.iter().find(|r#macro| r#macro.name() == name)
+    }
+
+    /// Get the `init` macro of the entry, which ftp clients run automatically after logging in.
+    pub fn init_macro(&self) -> Option<&Macro> {
+        self.macros.iter().find(|r#macro| r#macro.is_init())
+    }
+
+    /// Get the region of the input the entry was parsed from, starting at its `machine` or
+    /// `default` keyword.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Get the region of the input the login value was parsed from.
+    pub fn login_span(&self) -> Option<Span> {
+        self.login_span
+    }
+
+    /// Get the region of the input the password value was parsed from.
+    pub fn password_span(&self) -> Option<Span> {
+        self.password_span
+    }
+
+    /// Get the region of the input the account value was parsed from.
+    pub fn account_span(&self) -> Option<Span> {
+        self.account_span
+    }
+
+    /// Get the region of the input the port value was parsed from, if it is a valid port.
+    pub fn port_span(&self) -> Option<Span> {
+        self.port_span
+    }
+
+    /// Get the region of the input the protocol value was parsed from.
+    pub fn protocol_span(&self) -> Option<Span> {
+        self.protocol_span
+    }
+
+    /// Get the region of the input the path value was parsed from.
+    pub fn path_span(&self) -> Option<Span> {
+        self.path_span
+    }
+
+    pub(crate) fn has_values(&self) -> bool {
+        self.login.is_some()
+            || self.password.is_some()
+            || self.account.is_some()
+            || self.port.is_some()
+            || self.protocol.is_some()
+            || self.path.is_some()
+            || !self.macros.is_empty()
+    }
 }
 
 /// A lower-level netrc parser without any business rules related to it. Not recommended for most
@@ -36,7 +124,7 @@ impl RawEntry {
 #[derive(Debug)]
 pub struct RawNetrcParser<R: Read> {
     buffer: R,
-    config: Option<NetrcConfig>,
+    netrc: Option<Netrc>,
 }
 
 impl<R: Read> RawNetrcParser<R> {
@@ -44,7 +132,7 @@ impl<R: Read> RawNetrcParser<R> {
     pub fn new(buffer: R) -> Self {
         Self {
             buffer,
-            config: None,
+            netrc: None,
         }
     }
 
@@ -57,24 +145,46 @@ impl<R: Read> RawNetrcParser<R> {
     /// - `Ok(None)` if the host was not found and no default was setup
     /// - `Ok(Some)` if either a default was setup or the host was found
     pub fn entry_for_host(&mut self, host: &Host) -> Result<Option<RawEntry>> {
-        let mut buf_content = String::new();
-        self.buffer.read_to_string(&mut buf_content)?;
+        Ok(self.netrc()?.raw_entry_for_host(host).cloned())
+    }
 
-        let config = match &self.config {
-            Some(config) => config.clone(),
-            None => {
-                let config = parse_config(&buf_content);
-                self.config = Some(config.clone());
+    /// Parse the config file from the constructor and attempt to find the entry related to the
+    /// given machine name, following the same rules as [RawNetrcParser::entry_for_host]. The name
+    /// is parsed with [crate::host::HostKey::parse], so names that are not valid hosts match
+    /// entries with the exact same name.
+    ///
+    /// # Returns
+    ///
+    /// - An error if reading the input buffer failed
+    /// - `Ok(None)` if the name was not found and no default was setup
+    /// - `Ok(Some)` if either a default was setup or the name was found
+    pub fn entry_for_name(&mut self, name: &str) -> Result<Option<RawEntry>> {
+        Ok(self.netrc()?.raw_entry_for_name(name).cloned())
+    }
 
-                config
-            }
-        };
+    /// Parse the config file from the constructor and find every entry of the given host, in the
+    /// order they appear in the input. The default entry is not included.
+    ///
+    /// # Returns
+    ///
+    /// - An error if reading the input buffer failed
+    /// - `Ok` with the entries of the host, empty if the host was not found
+    pub fn entries_for_host(&mut self, host: &Host) -> Result<Vec<RawEntry>> {
+        Ok(self.netrc()?.raw_entries_for_host(host).cloned().collect())
+    }
 
-        Ok(config
-            .entries
-            .get(host)
-            .or(config.default.as_ref())
-            .cloned())
+    /// Parse the config file from the constructor, if not done before, and get the parsed
+    /// document. Use it for lookups the parser does not offer, like [Netrc::lookup].
+    ///
+    /// # Returns
+    ///
+    /// - An error if reading the input buffer failed
+    /// - `Ok` with the parsed document otherwise
+    pub fn netrc(&mut self) -> Result<&Netrc> {
+        match self.netrc {
+            Some(ref netrc) => Ok(netrc),
+            None => Ok(self.netrc.insert(Netrc::from_reader(&mut self.buffer)?)),
+        }
     }
 }
 
@@ -104,6 +214,16 @@ mod tests {
         notfound(SIMPLE, IP1);
     }
 
+    #[test]
+    fn parse_ip_config() {
+        const IP: &str = "
+            machine 1.1.1.1 login user password pass account acc
+        ";
+        found(IP, IP1, "user", "pass", "acc");
+        notfound(IP, IP2);
+        notfound(IP, COM);
+    }
+
     #[test]
     fn parse_empty_config() {
         const SIMPLE: &str = "
@@ -115,6 +235,120 @@ mod tests {
         notfound(SIMPLE, IP1);
     }
 
+    #[test]
+    fn parse_macros() {
+        const MACROS: &str = "
+machine example.com login user password pass
+macdef init
+cd /pub
+binary
+
+macdef upload
+put $1
+
+machine example.org login other
+";
+        let entry = entry(MACROS, COM);
+        let macros: Vec<_> = entry
+            .macros()
+            .iter()
+            .map(|r#macro| (r#macro.name(), r#macro.lines()))
+            .collect();
+
+        assert_eq!(
+            macros,
+            [
+                ("init", &["cd /pub".to_string(), "binary".to_string()][..]),
+                ("upload", &["put $1".to_string()][..]),
+            ]
+        );
+        assert_eq!(entry.password(), Some(&"pass".to_string()));
+        assert_eq!(entry.macro_by_name("upload").unwrap().lines(), ["put $1"]);
+        assert!(entry.macro_by_name("download").is_none());
+        assert_eq!(entry.init_macro().map(Macro::name), Some("init"));
+        assert!(self::entry(MACROS, ORG).macros().is_empty());
+    }
+
+    #[test]
+    fn parse_default_macros() {
+        const DEFAULT_MACRO: &str = "
+default login anonymous password guest
+macdef init
+ls
+
+";
+        let entry = entry(DEFAULT_MACRO, COM);
+
+        assert_eq!(entry.macro_by_name("init").unwrap().lines(), ["ls"]);
+    }
+
+    #[test]
+    fn parse_macro_outside_entry() {
+        const TOP_LEVEL_MACRO: &str = "
+macdef init
+ls
+
+machine example.com login user
+";
+        assert!(entry(TOP_LEVEL_MACRO, COM).macros().is_empty());
+    }
+
+    #[test]
+    fn parse_macro_with_crlf() {
+        const CRLF_MACRO: &str =
+            "machine example.com login user\r\nmacdef init\r\ncd /pub\r\n\r\nmachine example.org login other\r\n";
+        let entry = entry(CRLF_MACRO, COM);
+
+        assert_eq!(entry.macro_by_name("init").unwrap().lines(), ["cd /pub"]);
+        found(CRLF_MACRO, ORG, "other", None, None);
+    }
+
+    #[test]
+    fn parse_unterminated_macro_body() {
+        const UNTERMINATED: &str = "machine example.com login user\nmacdef init\ncd /pub\nbinary\n";
+        let entry = entry(UNTERMINATED, COM);
+
+        assert_eq!(
+            entry.macro_by_name("init").unwrap().lines(),
+            ["cd /pub", "binary"]
+        );
+    }
+
+    #[test]
+    fn parse_duplicate_machines() {
+        const DUPLICATES: &str = "
+            machine example.com login first
+            machine example.org login other
+            machine example.com login second
+        ";
+        found(DUPLICATES, COM, "first", None, None);
+
+        let entries = RawNetrcParser::new(DUPLICATES.as_bytes())
+            .entries_for_host(&Host::parse(COM).unwrap())
+            .unwrap();
+        let logins: Vec<_> = entries.iter().filter_map(RawEntry::login).collect();
+        assert_eq!(logins, ["first", "second"]);
+    }
+
+    #[test]
+    fn parse_opaque_machine() {
+        const OPAQUE: &str = "machine build:agent login user";
+        let entry = RawNetrcParser::new(OPAQUE.as_bytes())
+            .entry_for_name("build:agent")
+            .unwrap()
+            .expect("Didn't find entry");
+
+        assert_eq!(entry.login(), Some(&"user".to_string()));
+    }
+
+    #[track_caller]
+    fn entry(netrc: &str, host: &str) -> RawEntry {
+        RawNetrcParser::new(netrc.as_bytes())
+            .entry_for_host(&Host::parse(host).unwrap())
+            .unwrap()
+            .expect("Didn't find entry")
+    }
+
     #[track_caller]
     fn found(
         netrc: &str,
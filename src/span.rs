@@ -0,0 +1,150 @@
+use std::ops::Range;
+
+/// The text every secret is replaced with when showing parts of a netrc file.
+pub(crate) const MASK: &str = "****";
+
+/// A region of the parsed netrc input.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Span {
+    pub(crate) fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// The byte offset the region starts at.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The byte offset directly after the region.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// The byte range of the region, usable to index the input.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The line the region starts on, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column the region starts on in characters, starting at 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// A span from the start of this span to the end of the other.
+    pub(crate) fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+}
+
+/// Resolves byte offsets in the input to line and column positions.
+pub(crate) struct LineIndex<'a> {
+    input: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(newline, _)| newline + 1))
+            .collect();
+
+        Self { input, line_starts }
+    }
+
+    pub(crate) fn span(&self, start: usize, end: usize) -> Span {
+        let line = self
+            .line_starts
+            .partition_point(|&line_start| line_start <= start);
+        let column = self.input[self.line_starts[line - 1]..start]
+            .chars()
+            .count()
+            + 1;
+
+        Span::new(start, end, line, column)
+    }
+}
+
+/// The full lines of the input touched by the range, with every secret inside them masked.
+pub(crate) fn masked_lines(input: &str, range: Range<usize>, secrets: &[Span]) -> String {
+    let start = input[..range.start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let end = input[range.end..]
+        .find('\n')
+        .map_or(input.len(), |newline| range.end + newline);
+
+    let mut snippet = String::new();
+    let mut position = start;
+
+    for secret in secrets
+        .iter()
+        .filter(|secret| secret.start >= start && secret.end <= end)
+    {
+        snippet.push_str(&input[position..secret.start]);
+        snippet.push_str(MASK);
+        position = secret.end;
+    }
+
+    snippet.push_str(&input[position..end]);
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_positions() {
+        let input = "machine a\nlogin é\n\npassword p";
+        let index = LineIndex::new(input);
+
+        assert_eq!(index.span(0, 7), Span::new(0, 7, 1, 1));
+        assert_eq!(index.span(8, 9), Span::new(8, 9, 1, 9));
+        assert_eq!(index.span(10, 15).line(), 2);
+        assert_eq!(index.span(19, 20), Span::new(19, 20, 3, 1));
+        assert_eq!(index.span(20, 28), Span::new(20, 28, 4, 1));
+    }
+
+    #[test]
+    fn resolve_columns_in_characters() {
+        let input = "login é password p";
+        let index = LineIndex::new(input);
+
+        assert_eq!(index.span(9, 17).column(), 9);
+    }
+
+    #[test]
+    fn mask_secrets_in_lines() {
+        let input = "machine a\nmachine b login u password secret\nmachine c password other";
+        let secrets = [Span::new(37, 43, 2, 28), Span::new(63, 68, 3, 20)];
+
+        assert_eq!(
+            masked_lines(input, 18..20, &secrets),
+            "machine b login u password ****"
+        );
+        assert_eq!(
+            masked_lines(input, 10..68, &secrets),
+            "machine b login u password ****\nmachine c password ****"
+        );
+    }
+}